@@ -1,235 +1,1716 @@
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::env;
 use std::fs;
 
 /// Token enum represents different types of tokens in Atomic.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 enum Token {
     Print,
+    Let,
+    If,
+    Else,
+    While,
+    True,
+    False,
+    And,
+    Or,
+    Not,
+    Func,
+    Call,
+    Return,
+    Define,
+    Comma,
+    /// Only significant to the macro-expansion pass, which uses it to find
+    /// the end of a `define` directive's body; stripped before parsing.
+    Newline,
+    Identifier(String),
+    Number(i64),
+    Float(f64),
+    String(String),
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    Eq,
+    Neq,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Equals,
+    LParen,
+    RParen,
+    LBrace,
+    RBrace,
+    LBracket,
+    RBracket,
+}
+
+/// BinOp represents the arithmetic operators usable inside an expression.
+#[derive(Debug, Clone, Copy)]
+enum BinOp {
     Add,
     Subtract,
     Multiply,
     Divide,
     Modulus,
-    Let,
-    Identifier(String),
-    Number(i32),
-    String(String),
 }
 
-/// Value enum represents a number or a variable name.
-#[derive(Debug)]
+/// CmpOp represents the comparison operators usable inside an expression.
+#[derive(Debug, Clone, Copy)]
+enum CmpOp {
+    Eq,
+    Neq,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+}
+
+/// LogicOp represents the binary logical operators usable inside an expression.
+#[derive(Debug, Clone, Copy)]
+enum LogicOp {
+    And,
+    Or,
+}
+
+/// Value is a runtime Atomic value. Arithmetic and comparisons between
+/// differently-typed values go through the [`coerce`] module rather than
+/// being hard-wired to a single numeric type.
+#[derive(Debug, Clone, PartialEq)]
 enum Value {
-    Number(i32),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Bool(bool),
+    List(Vec<Value>),
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Int(n) => write!(f, "{}", n),
+            Value::Float(n) => write!(f, "{}", n),
+            Value::Str(s) => write!(f, "{}", s),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::List(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+/// Expr is the Abstract Syntax Tree for arithmetic and boolean expressions.
+/// Unlike the old flat `Value`, a `BinaryOp` can nest arbitrarily deep, so
+/// expressions like `2 + 3 * (a - 1)` parse into a single tree instead of a
+/// sequence of flat statements.
+#[derive(Debug)]
+enum Expr {
+    Number(i64),
+    Float(f64),
+    Str(String),
+    Bool(bool),
     Variable(String),
+    BinaryOp(Box<Expr>, BinOp, Box<Expr>),
+    Comparison(Box<Expr>, CmpOp, Box<Expr>),
+    Logical(Box<Expr>, LogicOp, Box<Expr>),
+    Not(Box<Expr>),
+    Neg(Box<Expr>),
+    Call(String, Vec<Expr>),
+    ListLiteral(Vec<Expr>),
+    Index(Box<Expr>, Box<Expr>),
 }
 
-/// ASTNode represents parsed expressions in the Abstract Syntax Tree.
+/// ASTNode represents parsed statements in the Abstract Syntax Tree.
 #[derive(Debug)]
 enum ASTNode {
-    Print(String),
-    Add(Value, Value),
-    Subtract(Value, Value),
-    Multiply(Value, Value),
-    Divide(Value, Value),
-    Modulus(Value, Value),
-    Let(String, i32),
+    PrintExpr(Expr),
+    Let(String, Expr),
+    If(Expr, Vec<ASTNode>, Vec<ASTNode>),
+    While(Expr, Vec<ASTNode>),
+    FuncDef(String, Vec<String>, Vec<ASTNode>),
+    Return(Option<Expr>),
 }
 
 /// Lexer: Converts source code into a list of tokens.
 fn lexer(code: &str) -> Vec<Token> {
     let mut tokens = Vec::new();
-    let mut words = code.split_whitespace().peekable();
-
-    while let Some(word) = words.next() {
-        match word {
-            "print" => tokens.push(Token::Print),
-            "add" => tokens.push(Token::Add),
-            "subtract" => tokens.push(Token::Subtract),
-            "multiply" => tokens.push(Token::Multiply),
-            "divide" => tokens.push(Token::Divide),
-            "mod" => tokens.push(Token::Modulus),
-            "let" => {
-                tokens.push(Token::Let);
-                if let Some(var) = words.next() {
-                    if words.peek() == Some(&"=") {
-                        words.next(); // Consume '='
-                        if let Some(value) = words.next() {
-                            if let Ok(num) = value.parse::<i32>() {
-                                tokens.push(Token::Identifier(var.to_string()));
-                                tokens.push(Token::Number(num));
-                                continue;
-                            }
-                        }
+    let mut chars = code.chars().peekable();
+
+    while let Some(&ch) = chars.peek() {
+        match ch {
+            '\n' => {
+                tokens.push(Token::Newline);
+                chars.next();
+            }
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            c if c.is_ascii_digit() => {
+                let mut number = String::new();
+                let mut is_float = false;
+                while let Some(&c) = chars.peek() {
+                    if c.is_ascii_digit() {
+                        number.push(c);
+                        chars.next();
+                    } else if c == '.' && !is_float {
+                        is_float = true;
+                        number.push(c);
+                        chars.next();
+                    } else {
+                        break;
                     }
                 }
-                eprintln!("Syntax Error: Invalid variable assignment syntax");
-            }
-            _ => {
-                if let Ok(num) = word.parse::<i32>() {
-                    tokens.push(Token::Number(num));
-                } else if word.starts_with("\"") {
-                    let mut full_string = word.to_string();
-                    while let Some(next_word) = words.peek() {
-                        full_string.push(' ');
-                        full_string.push_str(next_word);
-                        if next_word.ends_with("\"") {
-                            words.next();
-                            break;
-                        } else {
-                            words.next();
-                        }
+                if is_float {
+                    match number.parse::<f64>() {
+                        Ok(n) => tokens.push(Token::Float(n)),
+                        Err(_) => eprintln!("Syntax Error: Invalid number literal '{}'", number),
                     }
-                    tokens.push(Token::String(full_string.trim_matches('"').to_string()));
-                } else if word.chars().all(|c| c.is_alphabetic()) {
-                    tokens.push(Token::Identifier(word.to_string()));
                 } else {
-                    eprintln!("Unknown token: {}", word);
+                    match number.parse::<i64>() {
+                        Ok(n) => tokens.push(Token::Number(n)),
+                        Err(_) => eprintln!("Syntax Error: Invalid number literal '{}'", number),
+                    }
+                }
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_alphanumeric() || c == '_' {
+                        word.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                match word.as_str() {
+                    "print" => tokens.push(Token::Print),
+                    "let" => tokens.push(Token::Let),
+                    "if" => tokens.push(Token::If),
+                    "else" => tokens.push(Token::Else),
+                    "while" => tokens.push(Token::While),
+                    "true" => tokens.push(Token::True),
+                    "false" => tokens.push(Token::False),
+                    "and" => tokens.push(Token::And),
+                    "or" => tokens.push(Token::Or),
+                    "not" => tokens.push(Token::Not),
+                    "eq" => tokens.push(Token::Eq),
+                    "neq" => tokens.push(Token::Neq),
+                    "lt" => tokens.push(Token::Lt),
+                    "gt" => tokens.push(Token::Gt),
+                    "le" => tokens.push(Token::Le),
+                    "ge" => tokens.push(Token::Ge),
+                    "func" => tokens.push(Token::Func),
+                    "call" => tokens.push(Token::Call),
+                    "return" => tokens.push(Token::Return),
+                    "define" => tokens.push(Token::Define),
+                    _ => tokens.push(Token::Identifier(word)),
                 }
             }
+            '"' => {
+                chars.next(); // consume opening quote
+                let mut text = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == '"' {
+                        closed = true;
+                        break;
+                    }
+                    text.push(c);
+                }
+                if !closed {
+                    eprintln!("Syntax Error: Unterminated string literal");
+                }
+                tokens.push(Token::String(text));
+            }
+            '+' => {
+                tokens.push(Token::Plus);
+                chars.next();
+            }
+            '-' => {
+                tokens.push(Token::Minus);
+                chars.next();
+            }
+            '*' => {
+                tokens.push(Token::Star);
+                chars.next();
+            }
+            '/' => {
+                tokens.push(Token::Slash);
+                chars.next();
+            }
+            '%' => {
+                tokens.push(Token::Percent);
+                chars.next();
+            }
+            '=' => {
+                tokens.push(Token::Equals);
+                chars.next();
+            }
+            '(' => {
+                tokens.push(Token::LParen);
+                chars.next();
+            }
+            ')' => {
+                tokens.push(Token::RParen);
+                chars.next();
+            }
+            '{' => {
+                tokens.push(Token::LBrace);
+                chars.next();
+            }
+            '}' => {
+                tokens.push(Token::RBrace);
+                chars.next();
+            }
+            ',' => {
+                tokens.push(Token::Comma);
+                chars.next();
+            }
+            '[' => {
+                tokens.push(Token::LBracket);
+                chars.next();
+            }
+            ']' => {
+                tokens.push(Token::RBracket);
+                chars.next();
+            }
+            _ => {
+                eprintln!("Unknown token: {}", ch);
+                chars.next();
+            }
         }
     }
 
     tokens
 }
 
+/// Expands `define` directives over the raw token stream before it reaches
+/// the parser, which never sees `Token::Define` or `Token::Newline`. Two
+/// forms are supported:
+/// - object-like: `define SIZE 100` — every later `SIZE` is replaced by `100`
+/// - function-like: `define double(x) x * 2` — every later `double(arg)` is
+///   replaced by the body with `x` substituted by `arg`'s tokens
+///
+/// A macro's body runs to the end of its source line, so directives rely on
+/// `Token::Newline` to know where the body ends.
+mod macros {
+    use super::Token;
+    use std::collections::HashMap;
+
+    /// Bails out expansion of a single macro use after this many nested
+    /// expansions, so a macro that (directly or indirectly) expands into
+    /// itself is reported as an error instead of looping forever.
+    const MAX_EXPANSION_DEPTH: usize = 64;
+
+    struct ObjectMacro {
+        body: Vec<Token>,
+    }
+
+    struct FuncMacro {
+        params: Vec<String>,
+        body: Vec<Token>,
+    }
+
+    type TokenIter = std::iter::Peekable<std::vec::IntoIter<Token>>;
+
+    pub(crate) fn expand(tokens: Vec<Token>) -> Vec<Token> {
+        let mut object_macros = HashMap::new();
+        let mut func_macros = HashMap::new();
+        expand_pass(tokens, &mut object_macros, &mut func_macros, 0)
+    }
+
+    fn expand_pass(
+        tokens: Vec<Token>,
+        object_macros: &mut HashMap<String, ObjectMacro>,
+        func_macros: &mut HashMap<String, FuncMacro>,
+        depth: usize,
+    ) -> Vec<Token> {
+        let mut out = Vec::new();
+        let mut iter = tokens.into_iter().peekable();
+
+        while let Some(token) = iter.next() {
+            match token {
+                Token::Define => define_directive(&mut iter, object_macros, func_macros),
+                Token::Newline => {}
+                Token::Identifier(name)
+                    if func_macros.contains_key(&name) && matches!(iter.peek(), Some(Token::LParen)) =>
+                {
+                    iter.next(); // consume '('
+                    let args = take_call_args(&mut iter);
+                    let def = &func_macros[&name];
+                    if args.len() != def.params.len() {
+                        eprintln!(
+                            "Macro Error: '{}' expects {} argument(s), found {}",
+                            name,
+                            def.params.len(),
+                            args.len()
+                        );
+                    } else if depth >= MAX_EXPANSION_DEPTH {
+                        eprintln!("Macro Error: '{}' exceeded maximum expansion depth", name);
+                    } else {
+                        let substituted = substitute(&def.body, &def.params, &args);
+                        out.extend(expand_pass(substituted, object_macros, func_macros, depth + 1));
+                    }
+                }
+                Token::Identifier(name) if object_macros.contains_key(&name) => {
+                    if depth >= MAX_EXPANSION_DEPTH {
+                        eprintln!("Macro Error: '{}' exceeded maximum expansion depth", name);
+                    } else {
+                        let body = object_macros[&name].body.clone();
+                        out.extend(expand_pass(body, object_macros, func_macros, depth + 1));
+                    }
+                }
+                other => out.push(other),
+            }
+        }
+
+        out
+    }
+
+    /// Parses the remainder of a `define` directive: a name, an optional
+    /// `(params)` list, and a body running to the next newline.
+    fn define_directive(
+        iter: &mut TokenIter,
+        object_macros: &mut HashMap<String, ObjectMacro>,
+        func_macros: &mut HashMap<String, FuncMacro>,
+    ) {
+        let name = match iter.next() {
+            Some(Token::Identifier(name)) => name,
+            other => {
+                eprintln!("Macro Error: Expected a name after 'define', found {:?}", other);
+                return;
+            }
+        };
+        if matches!(iter.peek(), Some(Token::LParen)) {
+            iter.next(); // consume '('
+            let params = take_params(iter);
+            let body = take_until_newline(iter);
+            func_macros.insert(name, FuncMacro { params, body });
+        } else {
+            let body = take_until_newline(iter);
+            object_macros.insert(name, ObjectMacro { body });
+        }
+    }
+
+    /// Collects tokens up to (and consuming) the next `Token::Newline`, or to
+    /// the end of the stream if there isn't one.
+    fn take_until_newline(iter: &mut TokenIter) -> Vec<Token> {
+        let mut body = Vec::new();
+        for token in iter.by_ref() {
+            if matches!(token, Token::Newline) {
+                break;
+            }
+            body.push(token);
+        }
+        body
+    }
+
+    /// Parses a comma-separated list of parameter names up to and including
+    /// the closing `)`.
+    fn take_params(iter: &mut TokenIter) -> Vec<String> {
+        let mut params = Vec::new();
+        if matches!(iter.peek(), Some(Token::RParen)) {
+            iter.next();
+            return params;
+        }
+        loop {
+            match iter.next() {
+                Some(Token::Identifier(name)) => params.push(name),
+                other => {
+                    eprintln!("Macro Error: Expected parameter name, found {:?}", other);
+                    return params;
+                }
+            }
+            match iter.next() {
+                Some(Token::Comma) => continue,
+                Some(Token::RParen) => break,
+                other => {
+                    eprintln!("Macro Error: Expected ',' or ')' in parameter list, found {:?}", other);
+                    break;
+                }
+            }
+        }
+        params
+    }
+
+    /// Parses a comma-separated list of call arguments up to and including
+    /// the closing `)`. Each argument is itself a token run, so nested
+    /// parentheses are tracked to avoid splitting on a comma that belongs to
+    /// an inner call.
+    fn take_call_args(iter: &mut TokenIter) -> Vec<Vec<Token>> {
+        let mut args = Vec::new();
+        if matches!(iter.peek(), Some(Token::RParen)) {
+            iter.next();
+            return args;
+        }
+        let mut current = Vec::new();
+        let mut paren_depth = 0;
+        loop {
+            match iter.next() {
+                Some(Token::Comma) if paren_depth == 0 => {
+                    args.push(std::mem::take(&mut current));
+                }
+                Some(Token::RParen) if paren_depth == 0 => {
+                    args.push(current);
+                    break;
+                }
+                Some(Token::LParen) => {
+                    paren_depth += 1;
+                    current.push(Token::LParen);
+                }
+                Some(Token::RParen) => {
+                    paren_depth -= 1;
+                    current.push(Token::RParen);
+                }
+                Some(other) => current.push(other),
+                None => {
+                    eprintln!("Macro Error: Unterminated argument list");
+                    break;
+                }
+            }
+        }
+        args
+    }
+
+    /// Replaces every occurrence of a parameter name in `body` with its
+    /// corresponding argument's tokens.
+    fn substitute(body: &[Token], params: &[String], args: &[Vec<Token>]) -> Vec<Token> {
+        let mut out = Vec::new();
+        for token in body {
+            if let Token::Identifier(name) = token {
+                if let Some(pos) = params.iter().position(|p| p == name) {
+                    out.extend(args[pos].iter().cloned());
+                    continue;
+                }
+            }
+            out.push(token.clone());
+        }
+        out
+    }
+}
+
+/// Returns the (left, right) binding power of an infix operator token, or
+/// `None` if the token cannot start an infix operator. Lowest to highest:
+/// `or`, `and`, comparisons, `+`/`-`, `*`/`/`/`%`.
+fn binding_power(token: &Token) -> Option<(u8, u8)> {
+    match token {
+        Token::Or => Some((1, 2)),
+        Token::And => Some((3, 4)),
+        Token::Eq | Token::Neq | Token::Lt | Token::Gt | Token::Le | Token::Ge => Some((5, 6)),
+        Token::Plus | Token::Minus => Some((10, 11)),
+        Token::Star | Token::Slash | Token::Percent => Some((20, 21)),
+        _ => None,
+    }
+}
+
+/// Binding power for the operand of a prefix `not`: tighter than any infix
+/// operator, so `not a eq b` requires parens (`not (a eq b)`) to combine
+/// `not` with a comparison or logical operator.
+const NOT_BP: u8 = 25;
+
+/// Binding power for the operand of a unary minus: tighter than any infix
+/// operator (including `*`/`/`/`%`), so `-a * b` parses as `(-a) * b`.
+const NEG_BP: u8 = 25;
+
+type TokenIter<'a> = std::iter::Peekable<std::slice::Iter<'a, Token>>;
+
+/// Parses a single primary expression: a literal, variable, `not` prefix,
+/// `call`, parenthesized sub-expression, or list literal. Used both as the
+/// Pratt parser's nud (in [`parse_expr`]) and directly to parse list literal
+/// elements, which are separated by whitespace rather than an operator and
+/// so must not themselves swallow a trailing index or infix operator.
+fn parse_primary(iter: &mut TokenIter) -> Option<Expr> {
+    match iter.next() {
+        Some(Token::Number(n)) => Some(Expr::Number(*n)),
+        Some(Token::Float(n)) => Some(Expr::Float(*n)),
+        Some(Token::String(s)) => Some(Expr::Str(s.clone())),
+        Some(Token::True) => Some(Expr::Bool(true)),
+        Some(Token::False) => Some(Expr::Bool(false)),
+        Some(Token::Identifier(name)) => Some(Expr::Variable(name.clone())),
+        Some(Token::Not) => Some(Expr::Not(Box::new(parse_expr(iter, NOT_BP)?))),
+        Some(Token::Minus) => Some(Expr::Neg(Box::new(parse_expr(iter, NEG_BP)?))),
+        Some(Token::Call) => {
+            let name = match iter.next() {
+                Some(Token::Identifier(name)) => name.clone(),
+                other => {
+                    eprintln!("Syntax Error: Expected function name after 'call', found {:?}", other);
+                    return None;
+                }
+            };
+            expect(iter, &Token::LParen, "'('")?;
+            let args = parse_args(iter)?;
+            Some(Expr::Call(name, args))
+        }
+        Some(Token::LParen) => {
+            let inner = parse_expr(iter, 0)?;
+            match iter.next() {
+                Some(Token::RParen) => {}
+                _ => {
+                    eprintln!("Syntax Error: Expected closing ')'");
+                    return None;
+                }
+            }
+            Some(inner)
+        }
+        Some(Token::LBracket) => {
+            let mut elements = Vec::new();
+            while !matches!(iter.peek(), Some(Token::RBracket) | None) {
+                // `-`/`not` prefixes are applied here: the prefix arms above
+                // parse their operand with `parse_expr`, which would take the
+                // next element as an index.
+                let mut prefixes = Vec::new();
+                while let Some(is_minus) = match iter.peek() {
+                    Some(Token::Minus) => Some(true),
+                    Some(Token::Not) => Some(false),
+                    _ => None,
+                } {
+                    prefixes.push(is_minus);
+                    iter.next();
+                }
+                let mut element = parse_primary(iter)?;
+                for is_minus in prefixes.into_iter().rev() {
+                    element = if is_minus { Expr::Neg(Box::new(element)) } else { Expr::Not(Box::new(element)) };
+                }
+                elements.push(element);
+            }
+            match iter.next() {
+                Some(Token::RBracket) => {}
+                _ => {
+                    eprintln!("Syntax Error: Expected closing ']'");
+                    return None;
+                }
+            }
+            Some(Expr::ListLiteral(elements))
+        }
+        other => {
+            eprintln!(
+                "Syntax Error: Expected a number, variable, '(' or '[' in expression, found {:?}",
+                other
+            );
+            None
+        }
+    }
+}
+
+/// Returns whether `token` can start a primary expression, i.e. whether it
+/// can appear directly after another expression to mean "index the
+/// preceding value by this one" (`xs 0`).
+fn starts_index_operand(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Number(_)
+            | Token::Float(_)
+            | Token::String(_)
+            | Token::True
+            | Token::False
+            | Token::Identifier(_)
+            | Token::LParen
+            | Token::LBracket
+    )
+}
+
+/// Parses a single expression using Pratt (precedence-climbing) parsing.
+/// `min_bp` is the minimum left binding power an infix operator must have
+/// for this call to keep consuming it; recursive calls raise `min_bp` to
+/// the operator's right binding power to build its right operand.
+fn parse_expr(iter: &mut TokenIter, min_bp: u8) -> Option<Expr> {
+    let mut lhs = parse_primary(iter)?;
+
+    while iter.peek().is_some_and(|t| starts_index_operand(t)) {
+        let index = parse_primary(iter)?;
+        lhs = Expr::Index(Box::new(lhs), Box::new(index));
+    }
+
+    while let Some((lbp, rbp)) = iter.peek().and_then(|t| binding_power(t)) {
+        if lbp < min_bp {
+            break;
+        }
+        let token = iter.next();
+        let rhs = parse_expr(iter, rbp)?;
+        lhs = match token {
+            Some(Token::Plus) => Expr::BinaryOp(Box::new(lhs), BinOp::Add, Box::new(rhs)),
+            Some(Token::Minus) => Expr::BinaryOp(Box::new(lhs), BinOp::Subtract, Box::new(rhs)),
+            Some(Token::Star) => Expr::BinaryOp(Box::new(lhs), BinOp::Multiply, Box::new(rhs)),
+            Some(Token::Slash) => Expr::BinaryOp(Box::new(lhs), BinOp::Divide, Box::new(rhs)),
+            Some(Token::Percent) => Expr::BinaryOp(Box::new(lhs), BinOp::Modulus, Box::new(rhs)),
+            Some(Token::Eq) => Expr::Comparison(Box::new(lhs), CmpOp::Eq, Box::new(rhs)),
+            Some(Token::Neq) => Expr::Comparison(Box::new(lhs), CmpOp::Neq, Box::new(rhs)),
+            Some(Token::Lt) => Expr::Comparison(Box::new(lhs), CmpOp::Lt, Box::new(rhs)),
+            Some(Token::Gt) => Expr::Comparison(Box::new(lhs), CmpOp::Gt, Box::new(rhs)),
+            Some(Token::Le) => Expr::Comparison(Box::new(lhs), CmpOp::Le, Box::new(rhs)),
+            Some(Token::Ge) => Expr::Comparison(Box::new(lhs), CmpOp::Ge, Box::new(rhs)),
+            Some(Token::And) => Expr::Logical(Box::new(lhs), LogicOp::And, Box::new(rhs)),
+            Some(Token::Or) => Expr::Logical(Box::new(lhs), LogicOp::Or, Box::new(rhs)),
+            _ => unreachable!(),
+        };
+    }
+
+    Some(lhs)
+}
+
+/// Parses a comma-separated list of call arguments up to and including the
+/// closing `)`.
+fn parse_args(iter: &mut TokenIter) -> Option<Vec<Expr>> {
+    let mut args = Vec::new();
+    if matches!(iter.peek(), Some(Token::RParen)) {
+        iter.next();
+        return Some(args);
+    }
+    loop {
+        args.push(parse_expr(iter, 0)?);
+        match iter.next() {
+            Some(Token::Comma) => continue,
+            Some(Token::RParen) => break,
+            other => {
+                eprintln!("Syntax Error: Expected ',' or ')' in argument list, found {:?}", other);
+                return None;
+            }
+        }
+    }
+    Some(args)
+}
+
+/// Parses a comma-separated list of parameter names up to and including the
+/// closing `)`.
+fn parse_params(iter: &mut TokenIter) -> Option<Vec<String>> {
+    let mut params = Vec::new();
+    if matches!(iter.peek(), Some(Token::RParen)) {
+        iter.next();
+        return Some(params);
+    }
+    loop {
+        match iter.next() {
+            Some(Token::Identifier(name)) => params.push(name.clone()),
+            other => {
+                eprintln!("Syntax Error: Expected parameter name, found {:?}", other);
+                return None;
+            }
+        }
+        match iter.next() {
+            Some(Token::Comma) => continue,
+            Some(Token::RParen) => break,
+            other => {
+                eprintln!("Syntax Error: Expected ',' or ')' in parameter list, found {:?}", other);
+                return None;
+            }
+        }
+    }
+    Some(params)
+}
+
 /// Parser: Converts tokens into an AST.
 fn parser(tokens: Vec<Token>) -> Vec<ASTNode> {
     let mut ast = Vec::new();
     let mut iter = tokens.iter().peekable();
 
-    while let Some(token) = iter.next() {
-        match token {
-            Token::Print => {
-                if let Some(Token::String(text)) = iter.next() {
-                    ast.push(ASTNode::Print(text.clone()));
-                } else {
-                    eprintln!("Syntax Error: Expected a string after 'print'");
+    while iter.peek().is_some() {
+        if let Some(stmt) = parse_statement(&mut iter) {
+            ast.push(stmt);
+        }
+    }
+
+    ast
+}
+
+/// Parses a single statement. Returns `None` (after reporting a syntax
+/// error) if the statement could not be parsed.
+fn parse_statement(iter: &mut TokenIter) -> Option<ASTNode> {
+    match iter.next()? {
+        Token::Print => Some(ASTNode::PrintExpr(parse_expr(iter, 0)?)),
+        Token::Let => {
+            let name = match iter.next() {
+                Some(Token::Identifier(name)) => name.clone(),
+                other => {
+                    eprintln!("Syntax Error: Expected variable name after 'let', found {:?}", other);
+                    return None;
+                }
+            };
+            expect(iter, &Token::Equals, "'='")?;
+            let expr = parse_expr(iter, 0)?;
+            Some(ASTNode::Let(name, expr))
+        }
+        Token::If => {
+            let cond = parse_expr(iter, 0)?;
+            expect(iter, &Token::LBrace, "'{'")?;
+            let then_branch = parse_block(iter);
+            let else_branch = if matches!(iter.peek(), Some(Token::Else)) {
+                iter.next();
+                expect(iter, &Token::LBrace, "'{'")?;
+                parse_block(iter)
+            } else {
+                Vec::new()
+            };
+            Some(ASTNode::If(cond, then_branch, else_branch))
+        }
+        Token::While => {
+            let cond = parse_expr(iter, 0)?;
+            expect(iter, &Token::LBrace, "'{'")?;
+            let body = parse_block(iter);
+            Some(ASTNode::While(cond, body))
+        }
+        Token::Func => {
+            let name = match iter.next() {
+                Some(Token::Identifier(name)) => name.clone(),
+                other => {
+                    eprintln!("Syntax Error: Expected function name after 'func', found {:?}", other);
+                    return None;
                 }
+            };
+            expect(iter, &Token::LParen, "'('")?;
+            let params = parse_params(iter)?;
+            expect(iter, &Token::LBrace, "'{'")?;
+            let body = parse_block(iter);
+            Some(ASTNode::FuncDef(name, params, body))
+        }
+        Token::Return => {
+            let expr = match iter.peek() {
+                Some(Token::RBrace) | None => None,
+                _ => Some(parse_expr(iter, 0)?),
+            };
+            Some(ASTNode::Return(expr))
+        }
+        other => {
+            eprintln!("Syntax Error: Unexpected token at statement start: {:?}", other);
+            None
+        }
+    }
+}
+
+/// Parses statements until a closing `}`, which is consumed.
+fn parse_block(iter: &mut TokenIter) -> Vec<ASTNode> {
+    let mut stmts = Vec::new();
+    loop {
+        match iter.peek() {
+            Some(Token::RBrace) => {
+                iter.next();
+                break;
             }
-            Token::Let => {
-                if let (Some(Token::Identifier(var)), Some(Token::Number(value))) =
-                    (iter.next(), iter.next())
-                {
-                    ast.push(ASTNode::Let(var.clone(), *value));
-                } else {
-                    eprintln!("Syntax Error: Expected variable name and value after 'let'");
+            None => {
+                eprintln!("Syntax Error: Expected '}}' to close block");
+                break;
+            }
+            _ => {
+                if let Some(stmt) = parse_statement(iter) {
+                    stmts.push(stmt);
                 }
             }
-            Token::Add | Token::Subtract | Token::Multiply | Token::Divide | Token::Modulus => {
-                let first = iter.next();
-                let second = iter.next();
+        }
+    }
+    stmts
+}
 
-                let left_value = match first {
-                    Some(Token::Number(n)) => Value::Number(*n),
-                    Some(Token::Identifier(var)) => Value::Variable(var.clone()),
-                    _ => {
-                        eprintln!("Syntax Error: Expected a number or variable after operation");
-                        continue;
-                    }
-                };
-
-                let right_value = match second {
-                    Some(Token::Number(n)) => Value::Number(*n),
-                    Some(Token::Identifier(var)) => Value::Variable(var.clone()),
-                    _ => {
-                        eprintln!("Syntax Error: Expected a number or variable after operation");
-                        continue;
-                    }
-                };
+/// Consumes the next token if it matches `expected`, reporting a syntax
+/// error (and returning `None`) otherwise.
+fn expect(iter: &mut TokenIter, expected: &Token, description: &str) -> Option<()> {
+    match iter.next() {
+        Some(token) if std::mem::discriminant(token) == std::mem::discriminant(expected) => Some(()),
+        other => {
+            eprintln!("Syntax Error: Expected {}, found {:?}", description, other);
+            None
+        }
+    }
+}
+
+/// Coercion rules for arithmetic and comparison between [`Value`]s of
+/// possibly different types: `Int`/`Float` promote to `Float`, `Str` only
+/// supports concatenation via `+`, and anything else invalid returns a
+/// typed error instead of panicking.
+mod coerce {
+    use super::Value;
+    use std::cmp::Ordering;
+
+    fn type_name(value: &Value) -> &'static str {
+        match value {
+            Value::Int(_) => "int",
+            Value::Float(_) => "float",
+            Value::Str(_) => "string",
+            Value::Bool(_) => "bool",
+            Value::List(_) => "list",
+        }
+    }
+
+    /// Applies `int_op`/`float_op` to `left`/`right` after promoting a
+    /// mixed `Int`/`Float` pair to `Float`; any other pairing is a typed error.
+    fn numeric_op(
+        left: Value,
+        right: Value,
+        op_name: &str,
+        int_op: impl FnOnce(i64, i64) -> Result<i64, String>,
+        float_op: impl FnOnce(f64, f64) -> Result<f64, String>,
+    ) -> Result<Value, String> {
+        match (left, right) {
+            (Value::Int(a), Value::Int(b)) => int_op(a, b).map(Value::Int),
+            (Value::Float(a), Value::Float(b)) => float_op(a, b).map(Value::Float),
+            (Value::Int(a), Value::Float(b)) => float_op(a as f64, b).map(Value::Float),
+            (Value::Float(a), Value::Int(b)) => float_op(a, b as f64).map(Value::Float),
+            (l, r) => Err(format!(
+                "Cannot apply '{}' to {} and {}",
+                op_name,
+                type_name(&l),
+                type_name(&r)
+            )),
+        }
+    }
+
+    /// Wraps a checked `i64` operation, mapping overflow (`None`) to a typed
+    /// error instead of letting it panic in a debug build.
+    fn checked_int_op(op_name: &str, result: Option<i64>) -> Result<i64, String> {
+        result.ok_or_else(|| format!("Arithmetic overflow in '{}'", op_name))
+    }
 
-                let operation = match token {
-                    Token::Add => ASTNode::Add(left_value, right_value),
-                    Token::Subtract => ASTNode::Subtract(left_value, right_value),
-                    Token::Multiply => ASTNode::Multiply(left_value, right_value),
-                    Token::Divide => ASTNode::Divide(left_value, right_value),
-                    Token::Modulus => ASTNode::Modulus(left_value, right_value),
-                    _ => unreachable!(),
-                };
+    pub(crate) fn add(left: Value, right: Value) -> Result<Value, String> {
+        if let (Value::Str(a), Value::Str(b)) = (&left, &right) {
+            return Ok(Value::Str(format!("{}{}", a, b)));
+        }
+        numeric_op(left, right, "+", |a, b| checked_int_op("+", a.checked_add(b)), |a, b| Ok(a + b))
+    }
+
+    pub(crate) fn subtract(left: Value, right: Value) -> Result<Value, String> {
+        numeric_op(left, right, "-", |a, b| checked_int_op("-", a.checked_sub(b)), |a, b| Ok(a - b))
+    }
+
+    pub(crate) fn multiply(left: Value, right: Value) -> Result<Value, String> {
+        numeric_op(left, right, "*", |a, b| checked_int_op("*", a.checked_mul(b)), |a, b| Ok(a * b))
+    }
+
+    pub(crate) fn divide(left: Value, right: Value) -> Result<Value, String> {
+        numeric_op(
+            left,
+            right,
+            "/",
+            |a, b| if b != 0 { checked_int_op("/", a.checked_div(b)) } else { Err("Division by zero".to_string()) },
+            |a, b| if b != 0.0 { Ok(a / b) } else { Err("Division by zero".to_string()) },
+        )
+    }
+
+    pub(crate) fn modulus(left: Value, right: Value) -> Result<Value, String> {
+        numeric_op(
+            left,
+            right,
+            "%",
+            |a, b| if b != 0 { checked_int_op("%", a.checked_rem(b)) } else { Err("Modulus by zero".to_string()) },
+            |a, b| if b != 0.0 { Ok(a % b) } else { Err("Modulus by zero".to_string()) },
+        )
+    }
+
+    fn as_f64(value: &Value) -> Option<f64> {
+        match value {
+            Value::Int(n) => Some(*n as f64),
+            Value::Float(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// Equality across types: `Int`/`Float` compare numerically, everything
+    /// else must match both variant and value.
+    pub(crate) fn equals(left: &Value, right: &Value) -> bool {
+        match (as_f64(left), as_f64(right)) {
+            (Some(a), Some(b)) => a == b,
+            _ => left == right,
+        }
+    }
+
+    /// Orders two values numerically; non-numeric operands are a typed error.
+    pub(crate) fn order(left: &Value, right: &Value) -> Result<Ordering, String> {
+        let (Some(a), Some(b)) = (as_f64(left), as_f64(right)) else {
+            return Err(format!("Cannot compare {} and {}", type_name(left), type_name(right)));
+        };
+        a.partial_cmp(&b).ok_or_else(|| "Cannot compare NaN".to_string())
+    }
+
+    /// Unwraps a `Bool`, or reports a typed error for any other value.
+    pub(crate) fn expect_bool(value: Value) -> Result<bool, String> {
+        match value {
+            Value::Bool(b) => Ok(b),
+            other => Err(format!("Expected bool, found {}", type_name(&other))),
+        }
+    }
+}
+
+/// Built-in functions on [`Value::List`], reachable the same way as a
+/// user-defined function: `call len(xs)`, `call push(xs, 5)`, `call get(xs, 0)`.
+/// Looked up only once a name is absent from the compiled `functions` map,
+/// so a user-defined function of the same name always wins.
+mod builtins {
+    use super::Value;
+
+    fn type_name(value: &Value) -> &'static str {
+        match value {
+            Value::Int(_) => "int",
+            Value::Float(_) => "float",
+            Value::Str(_) => "string",
+            Value::Bool(_) => "bool",
+            Value::List(_) => "list",
+        }
+    }
+
+    fn expect_list(value: Value) -> Result<Vec<Value>, String> {
+        match value {
+            Value::List(items) => Ok(items),
+            other => Err(format!("Expected list, found {}", type_name(&other))),
+        }
+    }
+
+    fn expect_index(value: Value) -> Result<usize, String> {
+        match value {
+            Value::Int(n) if n >= 0 => Ok(n as usize),
+            Value::Int(n) => Err(format!("List index cannot be negative: {}", n)),
+            other => Err(format!("Expected int index, found {}", type_name(&other))),
+        }
+    }
+
+    fn expect_arity(name: &str, args: &[Value], expected: usize) -> Result<(), String> {
+        if args.len() == expected {
+            Ok(())
+        } else {
+            Err(format!("'{}' expects {} argument(s), got {}", name, expected, args.len()))
+        }
+    }
 
-                ast.push(operation);
+    /// Looks up `list[index]`, or a typed error if `list` isn't a list, the
+    /// index isn't a non-negative int, or the index is out of bounds.
+    /// Shared by the `xs 0` indexing operator and the `get` built-in.
+    pub(crate) fn index(list: Value, index: Value) -> Result<Value, String> {
+        let index = expect_index(index)?;
+        let items = expect_list(list)?;
+        items
+            .get(index)
+            .cloned()
+            .ok_or_else(|| format!("List index out of bounds: {} (length {})", index, items.len()))
+    }
+
+    /// Dispatches a built-in call by name, or reports it as undefined so the
+    /// caller's "Undefined function" error path stays in one place.
+    pub(crate) fn call(name: &str, mut args: Vec<Value>) -> Result<Value, String> {
+        match name {
+            "len" => {
+                expect_arity(name, &args, 1)?;
+                let items = expect_list(args.remove(0))?;
+                Ok(Value::Int(items.len() as i64))
+            }
+            "push" => {
+                expect_arity(name, &args, 2)?;
+                let value = args.remove(1);
+                let mut items = expect_list(args.remove(0))?;
+                items.push(value);
+                Ok(Value::List(items))
             }
-            _ => {}
+            "get" => {
+                expect_arity(name, &args, 2)?;
+                let index_arg = args.remove(1);
+                let list_arg = args.remove(0);
+                index(list_arg, index_arg)
+            }
+            _ => Err(format!("Undefined function '{}'", name)),
         }
     }
+}
 
-    ast
+/// Instruction is a single opcode in the flat bytecode program that `compile`
+/// lowers an AST into. Variables are referenced by interned slot index, so
+/// the VM never does a string lookup at run time.
+#[derive(Debug)]
+enum Instruction {
+    PushConst(Value),
+    LoadVar(u16),
+    LoadGlobal(u16),
+    StoreVar(u16),
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Eq,
+    Neq,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Not,
+    JumpIfFalse(usize),
+    Jump(usize),
+    Call(String, usize),
+    Return,
+    Print,
+    PrintVarSet(String, u16),
+    MakeList(usize),
+    Index,
+}
+
+/// A compiled function: its own instruction stream and its own slot space
+/// (parameters interned first, at slots `0..params.len()`), entirely
+/// separate from the caller's slots.
+#[derive(Debug)]
+struct Function {
+    params: Vec<String>,
+    slot_count: u16,
+    body: Vec<Instruction>,
 }
 
-/// Executor: Processes and executes the AST nodes.
-fn execute(ast: &[ASTNode]) {
-    let mut variables: HashMap<String, i32> = HashMap::new();
+/// Compiler: Lowers the AST into a flat instruction stream, interning each
+/// variable name into a numeric slot index the first time it is seen.
+/// Function definitions are compiled separately into `functions`, keyed by
+/// name, rather than inlined into the main instruction stream.
+fn compile(ast: &[ASTNode]) -> (Vec<Instruction>, HashMap<String, u16>, HashMap<String, Function>) {
+    let mut program = Vec::new();
+    let mut var_slots: HashMap<String, u16> = HashMap::new();
+    let mut functions: HashMap<String, Function> = HashMap::new();
+    compile_stmts(ast, &mut program, &mut var_slots, &mut functions, None);
+    (program, var_slots, functions)
+}
 
+/// Lowers a sequence of statements into `program`, appending to it in place
+/// so nested blocks (`if`/`while` bodies) share the same instruction stream
+/// and slot table as their enclosing scope. `globals` is `None` while
+/// compiling the top-level program (where `var_slots` already *is* the
+/// global table) and `Some` while compiling a function body, so a variable
+/// read that isn't a local can fall back to the enclosing global scope.
+fn compile_stmts(
+    ast: &[ASTNode],
+    program: &mut Vec<Instruction>,
+    var_slots: &mut HashMap<String, u16>,
+    functions: &mut HashMap<String, Function>,
+    mut globals: Option<&mut HashMap<String, u16>>,
+) {
     for node in ast {
         match node {
-            ASTNode::Print(text) => {
-                println!("{}", text);
-            }
-            ASTNode::Let(var, value) => {
-                variables.insert(var.clone(), *value);
-                println!("Variable {} set to {}", var, value);
-            }
-            ASTNode::Add(a, b) => {
-                let left = resolve_value(a, &variables);
-                let right = resolve_value(b, &variables);
-                println!("{} + {} = {}", left, right, left + right);
-            }
-            ASTNode::Subtract(a, b) => {
-                let left = resolve_value(a, &variables);
-                let right = resolve_value(b, &variables);
-                println!("{} - {} = {}", left, right, left - right);
-            }
-            ASTNode::Multiply(a, b) => {
-                let left = resolve_value(a, &variables);
-                let right = resolve_value(b, &variables);
-                println!("{} * {} = {}", left, right, left * right);
-            }
-            ASTNode::Divide(a, b) => {
-                let left = resolve_value(a, &variables);
-                let right = resolve_value(b, &variables);
-                if right != 0 {
-                    println!("{} / {} = {}", left, right, left / right);
+            ASTNode::PrintExpr(expr) => {
+                compile_expr(expr, program, var_slots, globals.as_deref_mut());
+                program.push(Instruction::Print);
+            }
+            ASTNode::Let(name, expr) => {
+                compile_expr(expr, program, var_slots, globals.as_deref_mut());
+                let slot = intern_slot(name, var_slots);
+                program.push(Instruction::StoreVar(slot));
+                program.push(Instruction::PrintVarSet(name.clone(), slot));
+            }
+            ASTNode::If(cond, then_branch, else_branch) => {
+                compile_expr(cond, program, var_slots, globals.as_deref_mut());
+                let jump_if_false = program.len();
+                program.push(Instruction::JumpIfFalse(0)); // backpatched below
+                compile_stmts(then_branch, program, var_slots, functions, globals.as_deref_mut());
+                if else_branch.is_empty() {
+                    program[jump_if_false] = Instruction::JumpIfFalse(program.len());
                 } else {
-                    eprintln!("Error: Division by zero");
+                    let jump_over_else = program.len();
+                    program.push(Instruction::Jump(0)); // backpatched below
+                    program[jump_if_false] = Instruction::JumpIfFalse(program.len());
+                    compile_stmts(else_branch, program, var_slots, functions, globals.as_deref_mut());
+                    program[jump_over_else] = Instruction::Jump(program.len());
                 }
             }
-            ASTNode::Modulus(a, b) => {
-                let left = resolve_value(a, &variables);
-                let right = resolve_value(b, &variables);
-                if right != 0 {
-                    println!("{} % {} = {}", left, right, left % right);
-                } else {
-                    eprintln!("Error: Modulus by zero");
+            ASTNode::While(cond, body) => {
+                let loop_start = program.len();
+                compile_expr(cond, program, var_slots, globals.as_deref_mut());
+                let jump_if_false = program.len();
+                program.push(Instruction::JumpIfFalse(0)); // backpatched below
+                compile_stmts(body, program, var_slots, functions, globals.as_deref_mut());
+                program.push(Instruction::Jump(loop_start));
+                program[jump_if_false] = Instruction::JumpIfFalse(program.len());
+            }
+            ASTNode::FuncDef(name, params, body) => {
+                let function = compile_function(params, body, functions, &mut globals, var_slots);
+                functions.insert(name.clone(), function);
+            }
+            ASTNode::Return(expr) => {
+                match expr {
+                    Some(expr) => compile_expr(expr, program, var_slots, globals.as_deref_mut()),
+                    None => program.push(Instruction::PushConst(Value::Int(0))),
+                }
+                program.push(Instruction::Return);
+            }
+        }
+    }
+}
+
+/// Compiles a function body into its own instruction stream with its own
+/// slot space, with parameters interned first so a call can bind them by
+/// position. A variable read that resolves to neither a parameter nor a
+/// local `let` falls back to the enclosing global scope: `globals` when
+/// compiling a nested function, or `enclosing_var_slots` (the table the
+/// `func` statement itself sits in) when compiling a top-level one.
+fn compile_function(
+    params: &[String],
+    body: &[ASTNode],
+    functions: &mut HashMap<String, Function>,
+    globals: &mut Option<&mut HashMap<String, u16>>,
+    enclosing_var_slots: &mut HashMap<String, u16>,
+) -> Function {
+    let mut func_program = Vec::new();
+    let mut func_slots: HashMap<String, u16> = HashMap::new();
+    for param in params {
+        intern_slot(param, &mut func_slots);
+    }
+    let func_globals = globals.as_deref_mut().unwrap_or(enclosing_var_slots);
+    compile_stmts(body, &mut func_program, &mut func_slots, functions, Some(func_globals));
+    Function {
+        params: params.to_vec(),
+        slot_count: func_slots.len() as u16,
+        body: func_program,
+    }
+}
+
+/// Lowers an expression tree into postfix bytecode: operands are pushed
+/// first, then the operator that consumes them. See [`compile_stmts`] for
+/// what `globals` means.
+fn compile_expr(
+    expr: &Expr,
+    program: &mut Vec<Instruction>,
+    var_slots: &mut HashMap<String, u16>,
+    mut globals: Option<&mut HashMap<String, u16>>,
+) {
+    match expr {
+        Expr::Number(n) => program.push(Instruction::PushConst(Value::Int(*n))),
+        Expr::Float(n) => program.push(Instruction::PushConst(Value::Float(*n))),
+        Expr::Str(s) => program.push(Instruction::PushConst(Value::Str(s.clone()))),
+        Expr::Bool(b) => program.push(Instruction::PushConst(Value::Bool(*b))),
+        Expr::Variable(name) => {
+            if let Some(slot) = var_slots.get(name) {
+                program.push(Instruction::LoadVar(*slot));
+            } else if let Some(globals) = globals {
+                let slot = intern_slot(name, globals);
+                program.push(Instruction::LoadGlobal(slot));
+            } else {
+                let slot = intern_slot(name, var_slots);
+                program.push(Instruction::LoadVar(slot));
+            }
+        }
+        Expr::BinaryOp(left, op, right) => {
+            compile_expr(left, program, var_slots, globals.as_deref_mut());
+            compile_expr(right, program, var_slots, globals.as_deref_mut());
+            program.push(match op {
+                BinOp::Add => Instruction::Add,
+                BinOp::Subtract => Instruction::Sub,
+                BinOp::Multiply => Instruction::Mul,
+                BinOp::Divide => Instruction::Div,
+                BinOp::Modulus => Instruction::Mod,
+            });
+        }
+        Expr::Comparison(left, op, right) => {
+            compile_expr(left, program, var_slots, globals.as_deref_mut());
+            compile_expr(right, program, var_slots, globals.as_deref_mut());
+            program.push(match op {
+                CmpOp::Eq => Instruction::Eq,
+                CmpOp::Neq => Instruction::Neq,
+                CmpOp::Lt => Instruction::Lt,
+                CmpOp::Gt => Instruction::Gt,
+                CmpOp::Le => Instruction::Le,
+                CmpOp::Ge => Instruction::Ge,
+            });
+        }
+        Expr::Logical(left, op, right) => {
+            compile_expr(left, program, var_slots, globals.as_deref_mut());
+            match op {
+                // `left and right`: if `left` is false, short-circuit to
+                // `false` without evaluating `right` at all.
+                LogicOp::And => {
+                    let jump_if_false = program.len();
+                    program.push(Instruction::JumpIfFalse(0)); // backpatched below
+                    compile_expr(right, program, var_slots, globals);
+                    // Double-negate to type-check `right` as a bool, so a
+                    // non-bool right operand is still a typed error.
+                    program.push(Instruction::Not);
+                    program.push(Instruction::Not);
+                    let jump_over_false = program.len();
+                    program.push(Instruction::Jump(0)); // backpatched below
+                    program[jump_if_false] = Instruction::JumpIfFalse(program.len());
+                    program.push(Instruction::PushConst(Value::Bool(false)));
+                    program[jump_over_false] = Instruction::Jump(program.len());
+                }
+                // `left or right`: if `left` is true, short-circuit to
+                // `true` without evaluating `right` at all.
+                LogicOp::Or => {
+                    let jump_if_false = program.len();
+                    program.push(Instruction::JumpIfFalse(0)); // backpatched below
+                    program.push(Instruction::PushConst(Value::Bool(true)));
+                    let jump_over_right = program.len();
+                    program.push(Instruction::Jump(0)); // backpatched below
+                    program[jump_if_false] = Instruction::JumpIfFalse(program.len());
+                    compile_expr(right, program, var_slots, globals);
+                    // Double-negate to type-check `right` as a bool, so a
+                    // non-bool right operand is still a typed error.
+                    program.push(Instruction::Not);
+                    program.push(Instruction::Not);
+                    program[jump_over_right] = Instruction::Jump(program.len());
                 }
             }
         }
+        Expr::Not(inner) => {
+            compile_expr(inner, program, var_slots, globals);
+            program.push(Instruction::Not);
+        }
+        Expr::Neg(inner) => {
+            program.push(Instruction::PushConst(Value::Int(0)));
+            compile_expr(inner, program, var_slots, globals);
+            program.push(Instruction::Sub);
+        }
+        Expr::Call(name, args) => {
+            for arg in args {
+                compile_expr(arg, program, var_slots, globals.as_deref_mut());
+            }
+            program.push(Instruction::Call(name.clone(), args.len()));
+        }
+        Expr::ListLiteral(elements) => {
+            for element in elements {
+                compile_expr(element, program, var_slots, globals.as_deref_mut());
+            }
+            program.push(Instruction::MakeList(elements.len()));
+        }
+        Expr::Index(list, index) => {
+            compile_expr(list, program, var_slots, globals.as_deref_mut());
+            compile_expr(index, program, var_slots, globals);
+            program.push(Instruction::Index);
+        }
     }
 }
 
-/// Helper Function: Resolves a value (number or variable).
-fn resolve_value(value: &Value, variables: &HashMap<String, i32>) -> i32 {
-    match value {
-        Value::Number(n) => *n,
-        Value::Variable(name) => *variables.get(name).unwrap_or(&0), // Default to 0 if variable not found
+/// Returns the slot index for `name`, interning it on first use.
+fn intern_slot(name: &str, var_slots: &mut HashMap<String, u16>) -> u16 {
+    let next = var_slots.len() as u16;
+    *var_slots.entry(name.to_string()).or_insert(next)
+}
+
+/// Prints the instruction stream with indices, e.g. for the `--disassemble` flag.
+fn disassemble(program: &[Instruction]) {
+    for (index, instr) in program.iter().enumerate() {
+        println!("{:04}: {:?}", index, instr);
     }
 }
 
-/// Main Function: Reads the file, runs the lexer, parser, and executor.
+/// VM: Executes the top-level program against a slot vector sized to the
+/// number of interned globals, with `functions` available for any `call`
+/// expressions it reaches.
+fn run(program: &[Instruction], slot_count: usize, functions: &HashMap<String, Function>) -> Result<(), String> {
+    let mut slots: Vec<Value> = vec![Value::Int(0); slot_count];
+    execute_chunk(program, &mut slots, None, functions)?;
+    Ok(())
+}
+
+/// Executes one chunk of bytecode (the top-level program, or a function
+/// body) against its own operand stack and its own `slots`. A `Call`
+/// recurses into this same function with a fresh `slots` vector sized to
+/// the callee, so each call gets its own frame; `Return` unwinds out of
+/// the recursive call with the returned value. Control flow within a chunk
+/// is driven by an explicit instruction pointer so `Jump`/`JumpIfFalse` can
+/// move it arbitrarily instead of always advancing by one.
+///
+/// `globals` is `None` only for the top-level program, whose own `slots`
+/// *is* the global scope; every call it makes hands that same scope down
+/// to the callee as `Some`, and nested calls just forward it unchanged, so
+/// a function body's `LoadGlobal` always reaches the one true global frame
+/// no matter how deep the call stack is.
+fn execute_chunk(
+    program: &[Instruction],
+    slots: &mut [Value],
+    globals: Option<&[Value]>,
+    functions: &HashMap<String, Function>,
+) -> Result<Option<Value>, String> {
+    let mut stack: Vec<Value> = Vec::new();
+    let mut ip = 0;
+
+    while ip < program.len() {
+        match &program[ip] {
+            Instruction::PushConst(value) => stack.push(value.clone()),
+            Instruction::LoadVar(slot) => stack.push(slots[*slot as usize].clone()),
+            Instruction::LoadGlobal(slot) => {
+                let globals = globals.ok_or("Internal Error: no global scope available")?;
+                stack.push(globals[*slot as usize].clone());
+            }
+            Instruction::StoreVar(slot) => {
+                let value = stack.pop().ok_or("Stack underflow")?;
+                slots[*slot as usize] = value;
+            }
+            Instruction::Add => binary_op(&mut stack, coerce::add)?,
+            Instruction::Sub => binary_op(&mut stack, coerce::subtract)?,
+            Instruction::Mul => binary_op(&mut stack, coerce::multiply)?,
+            Instruction::Div => binary_op(&mut stack, coerce::divide)?,
+            Instruction::Mod => binary_op(&mut stack, coerce::modulus)?,
+            Instruction::Eq => compare_op(&mut stack, |a, b| Ok(coerce::equals(a, b)))?,
+            Instruction::Neq => compare_op(&mut stack, |a, b| Ok(!coerce::equals(a, b)))?,
+            Instruction::Lt => compare_op(&mut stack, |a, b| Ok(coerce::order(a, b)? == Ordering::Less))?,
+            Instruction::Gt => compare_op(&mut stack, |a, b| Ok(coerce::order(a, b)? == Ordering::Greater))?,
+            Instruction::Le => {
+                compare_op(&mut stack, |a, b| Ok(coerce::order(a, b)? != Ordering::Greater))?
+            }
+            Instruction::Ge => compare_op(&mut stack, |a, b| Ok(coerce::order(a, b)? != Ordering::Less))?,
+            Instruction::Not => {
+                let value = stack.pop().ok_or("Stack underflow")?;
+                stack.push(Value::Bool(!coerce::expect_bool(value)?));
+            }
+            Instruction::JumpIfFalse(target) => {
+                let cond = stack.pop().ok_or("Stack underflow")?;
+                if !coerce::expect_bool(cond)? {
+                    ip = *target;
+                    continue;
+                }
+            }
+            Instruction::Jump(target) => {
+                ip = *target;
+                continue;
+            }
+            Instruction::Call(name, arg_count) => {
+                if let Some(function) = functions.get(name) {
+                    if *arg_count != function.params.len() {
+                        return Err(format!(
+                            "Function '{}' expects {} argument(s), got {}",
+                            name,
+                            function.params.len(),
+                            arg_count
+                        ));
+                    }
+                    let mut args = Vec::with_capacity(*arg_count);
+                    for _ in 0..*arg_count {
+                        args.push(stack.pop().ok_or("Stack underflow")?);
+                    }
+                    args.reverse();
+                    let mut callee_slots = vec![Value::Int(0); function.slot_count as usize];
+                    callee_slots[..args.len()].clone_from_slice(&args);
+                    let callee_globals = Some(globals.unwrap_or(slots));
+                    let result = execute_chunk(&function.body, &mut callee_slots, callee_globals, functions)?;
+                    stack.push(result.unwrap_or(Value::Int(0)));
+                } else {
+                    let mut args = Vec::with_capacity(*arg_count);
+                    for _ in 0..*arg_count {
+                        args.push(stack.pop().ok_or("Stack underflow")?);
+                    }
+                    args.reverse();
+                    stack.push(builtins::call(name, args)?);
+                }
+            }
+            Instruction::Return => {
+                let value = stack.pop().ok_or("Stack underflow")?;
+                return Ok(Some(value));
+            }
+            Instruction::Print => {
+                let value = stack.pop().ok_or("Stack underflow")?;
+                println!("{}", value);
+            }
+            Instruction::PrintVarSet(name, slot) => {
+                println!("Variable {} set to {}", name, slots[*slot as usize]);
+            }
+            Instruction::MakeList(count) => {
+                let mut items = Vec::with_capacity(*count);
+                for _ in 0..*count {
+                    items.push(stack.pop().ok_or("Stack underflow")?);
+                }
+                items.reverse();
+                stack.push(Value::List(items));
+            }
+            Instruction::Index => {
+                let index = stack.pop().ok_or("Stack underflow")?;
+                let list = stack.pop().ok_or("Stack underflow")?;
+                stack.push(builtins::index(list, index)?);
+            }
+        }
+        ip += 1;
+    }
+
+    Ok(None)
+}
+
+/// Pops the top two stack operands, applies `op` to them, and pushes the result.
+fn binary_op(
+    stack: &mut Vec<Value>,
+    op: impl FnOnce(Value, Value) -> Result<Value, String>,
+) -> Result<(), String> {
+    let right = stack.pop().ok_or("Stack underflow")?;
+    let left = stack.pop().ok_or("Stack underflow")?;
+    stack.push(op(left, right)?);
+    Ok(())
+}
+
+/// Pops the top two stack operands, applies a boolean-producing `op` to
+/// them by reference, and pushes the `Value::Bool` result.
+fn compare_op(
+    stack: &mut Vec<Value>,
+    op: impl FnOnce(&Value, &Value) -> Result<bool, String>,
+) -> Result<(), String> {
+    let right = stack.pop().ok_or("Stack underflow")?;
+    let left = stack.pop().ok_or("Stack underflow")?;
+    let result = op(&left, &right)?;
+    stack.push(Value::Bool(result));
+    Ok(())
+}
+
+/// Main Function: Reads the file, runs the lexer, parser, compiler, and VM.
 fn main() {
     let args: Vec<String> = env::args().collect();
-    if args.len() < 2 {
-        eprintln!("Usage: cargo run <filename.atomic>");
-        return;
+
+    let mut filename: Option<&String> = None;
+    let mut disassemble_flag = false;
+    for arg in &args[1..] {
+        if arg == "--disassemble" {
+            disassemble_flag = true;
+        } else {
+            filename = Some(arg);
+        }
     }
 
-    let filename = &args[1];
+    let Some(filename) = filename else {
+        eprintln!("Usage: cargo run <filename.atomic> [--disassemble]");
+        return;
+    };
     let code = fs::read_to_string(filename).expect("Failed to read file");
 
     let tokens = lexer(&code);
     println!("Tokens: {:?}", tokens);
 
+    let tokens = macros::expand(tokens);
+    println!("Expanded Tokens: {:?}", tokens);
+
     let ast = parser(tokens);
     println!("AST: {:?}", ast);
 
-    execute(&ast);
+    let (program, var_slots, functions) = compile(&ast);
+    if disassemble_flag {
+        disassemble(&program);
+    }
+
+    if let Err(err) = run(&program, var_slots.len(), &functions) {
+        eprintln!("Runtime Error: {}", err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs a full Atomic source string through the lexer, macro pass,
+    /// parser, compiler, and VM, returning whatever the top-level `return`
+    /// produced (or `None` if it never returns).
+    fn eval_source(source: &str) -> Result<Option<Value>, String> {
+        let tokens = lexer(source);
+        let tokens = macros::expand(tokens);
+        let ast = parser(tokens);
+        let (program, var_slots, functions) = compile(&ast);
+        let mut slots = vec![Value::Int(0); var_slots.len()];
+        execute_chunk(&program, &mut slots, None, &functions)
+    }
+
+    #[test]
+    fn infix_precedence_respects_operator_binding_power() {
+        let result = eval_source("return 2 + 3 * 4").unwrap();
+        assert_eq!(result, Some(Value::Int(14)));
+    }
+
+    #[test]
+    fn while_loop_sums_up_to_a_bound() {
+        let result = eval_source(
+            "let total = 0
+             let i = 0
+             while i lt 5 {
+                 let total = total + i
+                 let i = i + 1
+             }
+             return total",
+        )
+        .unwrap();
+        assert_eq!(result, Some(Value::Int(10)));
+    }
+
+    #[test]
+    fn if_takes_the_else_branch_when_the_condition_is_false() {
+        let result = eval_source(
+            "if 1 gt 2 {
+                 return 1
+             } else {
+                 return 2
+             }",
+        )
+        .unwrap();
+        assert_eq!(result, Some(Value::Int(2)));
+    }
+
+    #[test]
+    fn not_and_and_and_or_evaluate_correctly() {
+        assert_eq!(eval_source("return not false").unwrap(), Some(Value::Bool(true)));
+        assert_eq!(eval_source("return true and false").unwrap(), Some(Value::Bool(false)));
+        assert_eq!(eval_source("return true and true").unwrap(), Some(Value::Bool(true)));
+        assert_eq!(eval_source("return false or true").unwrap(), Some(Value::Bool(true)));
+        assert_eq!(eval_source("return false or false").unwrap(), Some(Value::Bool(false)));
+    }
+
+    /// Regression test: `and`/`or` must short-circuit, so a right-hand side
+    /// that would otherwise error (or have a side effect) never runs once
+    /// the left operand already determines the result.
+    #[test]
+    fn and_or_short_circuit_and_never_evaluate_the_right_operand() {
+        let result = eval_source("return false and (1 / 0 eq 0)").unwrap();
+        assert_eq!(result, Some(Value::Bool(false)));
+
+        let result = eval_source("return true or (1 / 0 eq 0)").unwrap();
+        assert_eq!(result, Some(Value::Bool(true)));
+    }
+
+    #[test]
+    fn coerce_promotes_mixed_int_float_arithmetic_to_float() {
+        let result = coerce::add(Value::Int(2), Value::Float(3.5)).unwrap();
+        assert_eq!(result, Value::Float(5.5));
+    }
+
+    #[test]
+    fn coerce_divide_by_zero_is_a_typed_error() {
+        let err = coerce::divide(Value::Int(4), Value::Int(0)).unwrap_err();
+        assert_eq!(err, "Division by zero");
+    }
+
+    #[test]
+    fn coerce_modulus_by_zero_is_a_typed_error() {
+        let err = coerce::modulus(Value::Float(4.0), Value::Float(0.0)).unwrap_err();
+        assert_eq!(err, "Modulus by zero");
+    }
+
+    #[test]
+    fn coerce_rejects_mismatched_types_with_a_typed_error() {
+        let err = coerce::subtract(Value::Str("a".to_string()), Value::Int(1)).unwrap_err();
+        assert_eq!(err, "Cannot apply '-' to string and int");
+    }
+
+    #[test]
+    fn coerce_add_overflow_is_a_typed_error_instead_of_a_panic() {
+        let err = coerce::add(Value::Int(i64::MAX), Value::Int(1)).unwrap_err();
+        assert_eq!(err, "Arithmetic overflow in '+'");
+    }
+
+    #[test]
+    fn coerce_multiply_overflow_is_a_typed_error_instead_of_a_panic() {
+        let err = coerce::multiply(Value::Int(i64::MAX), Value::Int(2)).unwrap_err();
+        assert_eq!(err, "Arithmetic overflow in '*'");
+    }
+
+    #[test]
+    fn coerce_divide_min_by_negative_one_is_a_typed_error_instead_of_a_panic() {
+        let err = coerce::divide(Value::Int(i64::MIN), Value::Int(-1)).unwrap_err();
+        assert_eq!(err, "Arithmetic overflow in '/'");
+    }
+
+    #[test]
+    fn recursive_function_calls_compute_factorial() {
+        let result = eval_source(
+            "func factorial(n) {
+                 if n le 1 {
+                     return 1
+                 }
+                 return n * call factorial(n - 1)
+             }
+             return call factorial(5)",
+        )
+        .unwrap();
+        assert_eq!(result, Some(Value::Int(120)));
+    }
+
+    #[test]
+    fn calling_a_function_with_the_wrong_arity_is_an_error() {
+        let err = eval_source(
+            "func add(a, b) {
+                 return a + b
+             }
+             return call add(1)",
+        )
+        .unwrap_err();
+        assert_eq!(err, "Function 'add' expects 2 argument(s), got 1");
+    }
+
+    /// Regression test: a function body that reads a variable it doesn't
+    /// declare itself must see the enclosing global binding, not a fresh
+    /// local slot defaulting to 0.
+    #[test]
+    fn function_body_reads_enclosing_global_variable() {
+        let result = eval_source(
+            "let x = 10
+             func f() {
+                 return x
+             }
+             return call f()",
+        )
+        .unwrap();
+        assert_eq!(result, Some(Value::Int(10)));
+    }
+
+    #[test]
+    fn builtins_len_push_and_get_operate_on_lists() {
+        let list = Value::List(vec![Value::Int(1), Value::Int(2)]);
+        assert_eq!(builtins::call("len", vec![list.clone()]).unwrap(), Value::Int(2));
+
+        let pushed = builtins::call("push", vec![list.clone(), Value::Int(3)]).unwrap();
+        assert_eq!(pushed, Value::List(vec![Value::Int(1), Value::Int(2), Value::Int(3)]));
+
+        let got = builtins::call("get", vec![list, Value::Int(1)]).unwrap();
+        assert_eq!(got, Value::Int(2));
+    }
+
+    #[test]
+    fn builtins_get_out_of_bounds_is_a_typed_error() {
+        let list = Value::List(vec![Value::Int(1)]);
+        let err = builtins::index(list, Value::Int(5)).unwrap_err();
+        assert_eq!(err, "List index out of bounds: 5 (length 1)");
+    }
+
+    #[test]
+    fn list_literal_and_indexing_operator_round_trip() {
+        let result = eval_source("return [10 20 30] 1").unwrap();
+        assert_eq!(result, Some(Value::Int(20)));
+    }
+
+    #[test]
+    fn object_macro_expands_to_its_body_everywhere_it_is_used() {
+        let result = eval_source(
+            "define SIZE 100
+             return SIZE + 1",
+        )
+        .unwrap();
+        assert_eq!(result, Some(Value::Int(101)));
+    }
+
+    #[test]
+    fn function_macro_substitutes_its_parameter_with_the_call_argument() {
+        let result = eval_source(
+            "define double(x) x * 2
+             return double(10)",
+        )
+        .unwrap();
+        assert_eq!(result, Some(Value::Int(20)));
+    }
+
+    /// A macro called with the wrong number of arguments expands to nothing
+    /// (after an error is reported), rather than the call tokens themselves.
+    #[test]
+    fn function_macro_called_with_wrong_arity_expands_to_nothing() {
+        let tokens = lexer(
+            "define double(x) x * 2
+             double(1, 2)",
+        );
+        let expanded = macros::expand(tokens);
+        assert!(
+            !format!("{:?}", expanded).contains("double"),
+            "expected the wrong-arity call to drop out of expansion, got {:?}",
+            expanded
+        );
+    }
+
+    /// A macro that (directly or indirectly) expands into itself must stop
+    /// at `MAX_EXPANSION_DEPTH` instead of recursing forever.
+    #[test]
+    fn self_referential_macro_stops_at_the_expansion_depth_guard() {
+        let tokens = lexer(
+            "define loop loop
+             loop",
+        );
+        let expanded = macros::expand(tokens);
+        assert!(
+            !format!("{:?}", expanded).contains("loop"),
+            "expected the runaway macro to drop out of expansion, got {:?}",
+            expanded
+        );
+    }
 }